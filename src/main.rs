@@ -84,16 +84,21 @@ impl<'a> Drop for Hid<'a> {
 }
 
 impl<'a> Hid<'a> {
-    fn new(uhid: &'a mut Uhid) -> Result<Hid<'a>, Box<std::error::Error>> {
+    fn new(uhid: &'a mut Uhid, target: &nfc::Target) -> Result<Hid<'a>, Box<std::error::Error>> {
+        let uid = target.uid().unwrap_or(&[]);
+        let uniq: String = uid.iter().map(|b| format!("{:02x}", b)).collect();
+        let product = uid.iter().fold(0u16, |acc, &b| acc.wrapping_mul(31).wrapping_add(b as u16));
+
         let mut req: uhid::uhid_event = unsafe { std::mem::zeroed() };
         req.type_ = uhid::uhid_event_type_UHID_CREATE2;
         unsafe {
             copy_bytes(b"U2F-NFC HID Emulation Device", &mut req.u.create2.name);
+            copy_bytes(uniq.as_bytes(), &mut req.u.create2.uniq);
             copy_bytes(FIDO_DESCRIPTOR, &mut req.u.create2.rd_data);
             req.u.create2.rd_size = FIDO_DESCRIPTOR.len() as u16;
             req.u.create2.bus = 0x5;
             req.u.create2.vendor = 0xfffe;
-            req.u.create2.product = 0x0000;
+            req.u.create2.product = product;
         }
 
         uhid.write(&req)?;
@@ -206,6 +211,8 @@ static APDU_SELECT: &'static [u8] = &[
     0x00, 0xa4, 0x04, 0x00, 0x08, 0xa0, 0x00, 0x00, 0x06, 0x47, 0x2f, 0x00, 0x01
 ];
 
+const CAPABILITY_CBOR: u8 = 0x04;
+
 fn parse_response(data: &[u8]) -> Result<&[u8], u16> {
     let status = ((data[data.len()-2] as u16) << 8) | (data[data.len()-1] as u16);
     if status == 0x9000 {
@@ -218,9 +225,200 @@ fn parse_response(data: &[u8]) -> Result<&[u8], u16> {
 static DEVICE_CHIP_ERROR_MESSAGE: &'static str =
     "Device chip error, retrying. Note that registration does not work over NFC on Yubikey tokens.";
 
+const MAX_APDU_CHAIN_ITERATIONS: usize = 16;
+
+#[derive(Debug)]
+enum ApduError {
+    Nfc(nfc::Error),
+    UnexpectedStatus(u8, u8),
+    TruncatedResponse,
+    ChainTooLong,
+}
+
+impl From<nfc::Error> for ApduError {
+    fn from(err: nfc::Error) -> ApduError {
+        ApduError::Nfc(err)
+    }
+}
+
+impl std::fmt::Display for ApduError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ApduError::Nfc(ref err) => write!(f, "{}", err),
+            ApduError::UnexpectedStatus(sw1, sw2) => write!(f, "unexpected APDU status {:02x}{:02x}", sw1, sw2),
+            ApduError::TruncatedResponse => write!(f, "APDU response shorter than the status word"),
+            ApduError::ChainTooLong => write!(f, "too many GET RESPONSE/resend round-trips"),
+        }
+    }
+}
+
+impl std::error::Error for ApduError {
+}
+
+const MAX_SHORT_APDU_DATA: usize = 255;
+
+fn split_chained_command(command: &[u8], max_block_len: usize) -> Vec<Vec<u8>> {
+    if command.len() <= 4 {
+        return vec![command.to_vec()];
+    }
+
+    let header = &command[0..4];
+
+    let (lc, data_start) = if command[4] == 0x00 && command.len() >= 7 {
+        (((command[5] as usize) << 8) | (command[6] as usize), 7)
+    } else {
+        (command[4] as usize, 5)
+    };
+
+    if data_start + lc > command.len() {
+        // Malformed/case 1-2 APDU; let the token reject it rather than panic.
+        return vec![command.to_vec()];
+    }
+
+    let data = &command[data_start..data_start + lc];
+    let le = if command.len() > data_start + lc { Some(command[command.len() - 1]) } else { None };
+
+    if data.len() <= max_block_len {
+        return vec![command.to_vec()];
+    }
+
+    let chunks: Vec<&[u8]> = data.chunks(max_block_len).collect();
+
+    chunks.iter().enumerate().map(|(i, chunk)| {
+        let last = i == chunks.len() - 1;
+
+        let mut block = Vec::with_capacity(5 + chunk.len() + 1);
+        block.push(if last { header[0] } else { header[0] | 0x10 });
+        block.extend_from_slice(&header[1..4]);
+        block.push(chunk.len() as u8);
+        block.extend_from_slice(chunk);
+        if last {
+            if let Some(le) = le {
+                block.push(le);
+            }
+        }
+        block
+    }).collect()
+}
+
+fn send_chaining_blocks(nfc_device: &mut nfc::Initiator, blocks: &[Vec<u8>]) -> Result<(), ApduError> {
+    for block in blocks {
+        let mut response = [0u8; 16];
+        let len = nfc_device.transceive(block, &mut response)?;
+        let response = &response[0..len];
+
+        if response.len() < 2 {
+            return Err(ApduError::TruncatedResponse);
+        }
+
+        let (sw1, sw2) = (response[response.len() - 2], response[response.len() - 1]);
+        if (sw1, sw2) != (0x90, 0x00) {
+            return Err(ApduError::UnexpectedStatus(sw1, sw2));
+        }
+    }
+
+    Ok(())
+}
+
+fn build_apdu(cla: u8, ins: u8, p1: u8, p2: u8, data: &[u8]) -> Vec<u8> {
+    let mut command = vec![cla, ins, p1, p2];
+
+    if data.len() <= MAX_SHORT_APDU_DATA {
+        command.push(data.len() as u8);
+        command.extend_from_slice(data);
+        command.push(0x00);
+    } else {
+        command.push(0x00);
+        command.push((data.len() >> 8) as u8);
+        command.push((data.len() & 0xff) as u8);
+        command.extend_from_slice(data);
+        command.push(0x00);
+        command.push(0x00);
+    }
+
+    command
+}
+
+enum TransceiveOutcome {
+    Data(Vec<u8>),
+    DeviceLost,
+    ChipError,
+}
+
+fn transceive_apdu_with_retries(nfc_device: &mut nfc::Initiator, command: &[u8]) -> Result<TransceiveOutcome, ApduError> {
+    match transceive_apdu(nfc_device, command) {
+        Ok(data) => Ok(TransceiveOutcome::Data(data)),
+        Err(ApduError::Nfc(nfc::Error::RfTransmissionError)) => Ok(TransceiveOutcome::DeviceLost),
+        Err(ApduError::Nfc(nfc::Error::DeviceChipError)) => Ok(TransceiveOutcome::ChipError),
+        Err(err) => Err(err),
+    }
+}
+
+fn transceive_apdu(nfc_device: &mut nfc::Initiator, command: &[u8]) -> Result<Vec<u8>, ApduError> {
+    let blocks = split_chained_command(command, MAX_SHORT_APDU_DATA);
+    let (chaining_blocks, last_block) = blocks.split_at(blocks.len() - 1);
+
+    send_chaining_blocks(nfc_device, chaining_blocks)?;
+
+    let mut command = last_block[0].clone();
+    let mut data = Vec::new();
+
+    for _ in 0..MAX_APDU_CHAIN_ITERATIONS {
+        let mut response = [0u8; 65536];
+        let len = nfc_device.transceive(&command, &mut response)?;
+        let response = &response[0..len];
+
+        if response.len() < 2 {
+            return Err(ApduError::TruncatedResponse);
+        }
+
+        let (body, sw) = response.split_at(response.len() - 2);
+        let (sw1, sw2) = (sw[0], sw[1]);
+
+        match sw1 {
+            0x90 if sw2 == 0x00 => {
+                data.extend_from_slice(body);
+                data.extend_from_slice(&[0x90, 0x00]);
+                return Ok(data);
+            }
+            0x61 => {
+                data.extend_from_slice(body);
+                let cla = command[0];
+                command = vec![cla, 0xc0, 0x00, 0x00, sw2];
+            }
+            0x6c => {
+                let le = command.len() - 1;
+                command[le] = sw2;
+            }
+            _ => {
+                // Any other SW (e.g. 6985 "conditions of use not satisfied",
+                // the normal response while waiting for a touch) isn't a
+                // transport retry code; relay it to the host unmodified, the
+                // same way a single untranslated transceive used to.
+                data.extend_from_slice(body);
+                data.extend_from_slice(sw);
+                return Ok(data);
+            }
+        }
+    }
+
+    Err(ApduError::ChainTooLong)
+}
+
 fn main() -> Result<(), Box<std::error::Error>> {
     let mut nfc_context = nfc::Context::new()?;
-    let mut nfc_device = nfc_context.open_initiator()?;
+
+    if std::env::args().any(|arg| arg == "--list-devices") {
+        for connstring in nfc_context.list_devices() {
+            println!("{}", connstring);
+        }
+        return Ok(());
+    }
+
+    let mut nfc_device = match std::env::var("NFC_CONNSTRING") {
+        Ok(connstring) => nfc_context.open_initiator_by_connstring(&connstring)?,
+        Err(_) => nfc_context.open_initiator()?,
+    };
     let mut uhid = Uhid::new()?;
 
     privdrop::PrivDrop::default()
@@ -230,8 +428,13 @@ fn main() -> Result<(), Box<std::error::Error>> {
 
     loop {
         /* Find NFC device. */
-        while nfc_device.poll_target()?.is_none() {
-        }
+        let target = loop {
+            if let Some(target) = nfc_device.poll_target()? {
+                break target;
+            }
+        };
+
+        eprintln!("Target modulation: {:?}", target.modulation());
 
         let mut response = [0u8; 16];
         let len = match nfc_device.transceive(APDU_SELECT, &mut response) {
@@ -245,11 +448,12 @@ fn main() -> Result<(), Box<std::error::Error>> {
         parse_response(&response[0..len])
             .map_err(|_| "Error status received from APDU SELECT")?;
 
-        eprintln!("Found NFC device.");
+        eprintln!("Found NFC device. UID={:02x?} ATQA={:02x?} SAK={:02x?} ATS={:02x?}",
+                  target.uid(), target.atqa(), target.sak(), target.ats());
 
         /* Got NFC device. */
 
-        let mut hid = Hid::new(&mut uhid)?;
+        let mut hid = Hid::new(&mut uhid, &target)?;
 
         loop {
             let msg = hid.read()?;
@@ -264,31 +468,61 @@ fn main() -> Result<(), Box<std::error::Error>> {
                     reply.extend_from_slice(&[0xaa, 0xbb, 0xcc, 0xdd,
                                               0x1,
                                               0x1, 0x0, 0x0,
-                                              0x0,
+                                              CAPABILITY_CBOR,
                                             ]);
 
                     hid.write(&HidMessage { cid: 0xffff_ffff, cmd: 0x6, data: reply })?;
                 }
                 (_, 0x3) => {
                     // HID_MSG
-                    let mut response = [0u8; 65536];
-                    let len = match nfc_device.transceive(&msg.data, &mut response) {
-                        Ok(len) => len,
-                        Err(nfc::Error::RfTransmissionError) => {
-                            // Lost device, retry
+                    let data = match transceive_apdu_with_retries(&mut nfc_device, &msg.data)? {
+                        TransceiveOutcome::Data(data) => data,
+                        TransceiveOutcome::DeviceLost => {
+                            eprintln!("NFC device lost.");
+                            break;
+                        }
+                        TransceiveOutcome::ChipError => {
+                            eprintln!("{}", DEVICE_CHIP_ERROR_MESSAGE);
+                            continue;
+                        }
+                    };
+
+                    let msg = HidMessage { cid: msg.cid, cmd: 0x3, data };
+                    hid.write(&msg)?;
+                }
+                (_, 0x10) => {
+                    // HID_CBOR: wrap the CTAP2 CBOR payload in the
+                    // NFCCTAP_MSG APDU (CLA 0x00, INS 0x10) and unwrap the
+                    // token's status word on return.
+                    let command = build_apdu(0x00, 0x10, 0x00, 0x00, &msg.data);
+
+                    let response = match transceive_apdu_with_retries(&mut nfc_device, &command)? {
+                        TransceiveOutcome::Data(data) => data,
+                        TransceiveOutcome::DeviceLost => {
                             eprintln!("NFC device lost.");
                             break;
                         }
-                        Err(nfc::Error::DeviceChipError) => {
+                        TransceiveOutcome::ChipError => {
                             eprintln!("{}", DEVICE_CHIP_ERROR_MESSAGE);
                             continue;
                         }
-                        err => err?,
                     };
 
-                    let buf = &response[0..len];
+                    let cbor = match parse_response(&response) {
+                        Ok(cbor) => cbor,
+                        Err(status) => {
+                            // E.g. legacy U2F-only tokens reject NFCCTAP_MSG
+                            // (INS 0x10) with 6D00; tell the host rather than
+                            // taking the whole bridge down over it.
+                            eprintln!("NFCCTAP_MSG failed with status 0x{:04x}", status);
+
+                            let reply = vec![0x01];
+                            hid.write(&HidMessage { cid: msg.cid, cmd: 0x3f, data: reply })?;
+                            continue;
+                        }
+                    };
 
-                    let msg = HidMessage { cid: msg.cid, cmd: 0x3, data: buf.to_owned() };
+                    let msg = HidMessage { cid: msg.cid, cmd: 0x10, data: cbor.to_owned() };
                     hid.write(&msg)?;
                 }
                 _ => {
@@ -95,8 +95,18 @@ impl Context {
     }
 
     pub fn open_initiator(&mut self) -> Result<Initiator, Error> {
+        self.open_initiator_with_connstring(std::ptr::null())
+    }
+
+    pub fn open_initiator_by_connstring(&mut self, connstring: &str) -> Result<Initiator, Error> {
+        let connstring = std::ffi::CString::new(connstring).map_err(|_| Error::InvalidArgument)?;
+
+        self.open_initiator_with_connstring(connstring.as_ptr() as *const nfc_sys::nfc_connstring)
+    }
+
+    fn open_initiator_with_connstring(&mut self, connstring: *const nfc_sys::nfc_connstring) -> Result<Initiator, Error> {
         let ptr = unsafe {
-            nfc_sys::nfc_open(self.get(), std::ptr::null())
+            nfc_sys::nfc_open(self.get(), connstring)
         };
 
         if ptr.is_null() {
@@ -113,6 +123,21 @@ impl Context {
             Ok(Initiator(ptr, self.0.clone()))
         }
     }
+
+    pub fn list_devices(&self) -> Vec<String> {
+        const MAX_DEVICES: usize = 16;
+
+        let mut connstrings: [nfc_sys::nfc_connstring; MAX_DEVICES] = unsafe { std::mem::zeroed() };
+
+        let count = unsafe {
+            nfc_sys::nfc_list_devices(self.get(), connstrings.as_mut_ptr(), MAX_DEVICES)
+        };
+
+        connstrings[0..count as usize].iter().map(|connstring| {
+            let cstr = unsafe { std::ffi::CStr::from_ptr(connstring.as_ptr()) };
+            cstr.to_string_lossy().into_owned()
+        }).collect()
+    }
 }
 
 impl Drop for Context {
@@ -131,15 +156,15 @@ impl Initiator {
     }
 
     pub fn poll_target(&mut self) -> Result<Option<Target>, Error> {
-        let modulation = nfc_sys::nfc_modulation {
-            nmt: nfc_sys::nfc_modulation_type::NMT_ISO14443A,
-            nbr: nfc_sys::nfc_baud_rate::NBR_106,
-        };
+        self.poll_targets(&Self::default_modulations())
+    }
 
+    pub fn poll_targets(&mut self, modulations: &[nfc_sys::nfc_modulation]) -> Result<Option<Target>, Error> {
         let mut target = nfc_sys::nfc_target::default();
 
         let err = unsafe {
-            nfc_sys::nfc_initiator_poll_target(self.get(), &modulation, 1, 1, 1, &mut target)
+            nfc_sys::nfc_initiator_poll_target(self.get(), modulations.as_ptr(), modulations.len(),
+                                                1, 1, &mut target)
         };
 
         if err < 0 {
@@ -151,6 +176,19 @@ impl Initiator {
         }
     }
 
+    pub fn default_modulations() -> Vec<nfc_sys::nfc_modulation> {
+        use nfc_sys::nfc_modulation_type::*;
+        use nfc_sys::nfc_baud_rate::*;
+
+        vec![
+            nfc_sys::nfc_modulation { nmt: NMT_ISO14443A, nbr: NBR_848 },
+            nfc_sys::nfc_modulation { nmt: NMT_ISO14443A, nbr: NBR_424 },
+            nfc_sys::nfc_modulation { nmt: NMT_ISO14443A, nbr: NBR_212 },
+            nfc_sys::nfc_modulation { nmt: NMT_ISO14443A, nbr: NBR_106 },
+            nfc_sys::nfc_modulation { nmt: NMT_ISO14443B, nbr: NBR_106 },
+        ]
+    }
+
     pub fn transceive(&mut self, transmit: &[u8], receive: &mut [u8]) -> Result<usize, Error> {
         let err = unsafe {
             nfc_sys::nfc_initiator_transceive_bytes(self.get(), transmit.as_ptr(), transmit.len(),
@@ -166,3 +204,33 @@ impl Initiator {
 }
 
 pub struct Target(nfc_sys::nfc_target);
+
+impl Target {
+    pub fn modulation(&self) -> nfc_sys::nfc_modulation {
+        self.0.nm
+    }
+
+    fn iso14443a_info(&self) -> Option<&nfc_sys::nfc_iso14443a_info> {
+        if self.0.nm.nmt == nfc_sys::nfc_modulation_type::NMT_ISO14443A {
+            Some(unsafe { &self.0.nti.nai })
+        } else {
+            None
+        }
+    }
+
+    pub fn uid(&self) -> Option<&[u8]> {
+        self.iso14443a_info().map(|info| &info.abtUid[0..info.szUidLen])
+    }
+
+    pub fn atqa(&self) -> Option<[u8; 2]> {
+        self.iso14443a_info().map(|info| info.abtAtqa)
+    }
+
+    pub fn sak(&self) -> Option<u8> {
+        self.iso14443a_info().map(|info| info.btSak)
+    }
+
+    pub fn ats(&self) -> Option<&[u8]> {
+        self.iso14443a_info().map(|info| &info.abtAts[0..info.szAtsLen])
+    }
+}